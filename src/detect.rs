@@ -1,6 +1,8 @@
 /// NOTE!!! these are taken from noodles-util
 /// by Michael Macias under MIT license
-use std::io::{self, BufRead, Read};
+use std::io::{BufRead, Read};
+
+use crate::error::{Error, Result};
 
 /// A variant format.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -16,29 +18,37 @@ pub enum Format {
 pub enum Compression {
     /// BGZF compression.
     Bgzf,
+    /// Zstandard compression.
+    Zstd,
+    /// Raw, uncompressed data.
+    None,
 }
 
-pub(crate) fn detect_compression<R>(reader: &mut R) -> io::Result<Option<Compression>>
+pub(crate) fn detect_compression<R>(reader: &mut R) -> Result<Compression>
 where
     R: BufRead,
 {
     const GZIP_MAGIC_NUMBER: [u8; 2] = [0x1f, 0x8b];
+    const ZSTD_MAGIC_NUMBER: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
 
     let src = reader.fill_buf()?;
 
     if let Some(buf) = src.get(..GZIP_MAGIC_NUMBER.len()) {
         if buf == GZIP_MAGIC_NUMBER {
-            return Ok(Some(Compression::Bgzf));
+            return Ok(Compression::Bgzf);
+        }
+    }
+
+    if let Some(buf) = src.get(..ZSTD_MAGIC_NUMBER.len()) {
+        if buf == ZSTD_MAGIC_NUMBER {
+            return Ok(Compression::Zstd);
         }
     }
 
-    Ok(None)
+    Ok(Compression::None)
 }
 
-pub(crate) fn detect_format<R>(
-    reader: &mut R,
-    compression: Option<Compression>,
-) -> io::Result<Format>
+pub(crate) fn detect_format<R>(reader: &mut R, compression: Compression) -> Result<Format>
 where
     R: BufRead,
 {
@@ -49,41 +59,166 @@ where
 
     let src = reader.fill_buf()?;
 
-    if let Some(compression) = compression {
-        if compression == Compression::Bgzf {
+    match compression {
+        Compression::Bgzf => {
             let mut decoder = MultiGzDecoder::new(src);
-            let mut buf = [0; BCF_MAGIC_NUMBER.len()];
-            decoder.read_exact(&mut buf)?;
-
-            if buf == BCF_MAGIC_NUMBER {
-                return Ok(Format::Bcf);
+            sniff_decoded(&mut decoder)
+        }
+        Compression::Zstd => {
+            let mut decoder = zstd::stream::read::Decoder::new(src)?;
+            sniff_decoded(&mut decoder)
+        }
+        Compression::None => {
+            if let Some(buf) = src.get(..BCF_MAGIC_NUMBER.len()) {
+                if buf == BCF_MAGIC_NUMBER {
+                    return Ok(Format::Bcf);
+                }
             }
-            // check that the file is a VCF file. should start with ##fileformat=VCF
-            let mut buf = [0; VCF_HEADER.len()];
-            decoder.read_exact(&mut buf)?;
-            if buf == VCF_HEADER {
-                return Ok(Format::Vcf);
+            // check for vcf format
+            if let Some(buf) = src.get(..VCF_HEADER.len()) {
+                if buf == VCF_HEADER {
+                    return Ok(Format::Vcf);
+                }
             }
-            // return error about unknown format
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown format"));
-        }
-        // return error about compression not supported
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "unsupported compression",
-        ));
-    } else if let Some(buf) = src.get(..BCF_MAGIC_NUMBER.len()) {
-        if buf == BCF_MAGIC_NUMBER {
-            return Ok(Format::Bcf);
+
+            Err(Error::UnknownFormat {
+                observed: src[..src.len().min(VCF_HEADER.len())].to_vec(),
+            })
         }
     }
-    // check for vcf format
-    if let Some(buf) = src.get(..VCF_HEADER.len()) {
-        if buf == VCF_HEADER {
-            return Ok(Format::Vcf);
-        }
+}
+
+/// Reads the first frame of a decompressed stream and checks it against the
+/// BCF/VCF magic, the way the BGZF branch always has.
+fn sniff_decoded<R: Read>(decoder: &mut R) -> Result<Format> {
+    const BCF_MAGIC_NUMBER: [u8; 3] = *b"BCF";
+    const VCF_HEADER: [u8; 16] = *b"##fileformat=VCF";
+
+    // Read once and check both magics against that same leading slice --
+    // reading BCF_MAGIC_NUMBER's bytes first and then VCF_HEADER's bytes
+    // would compare against two different, disjoint offsets.
+    let mut buf = [0; VCF_HEADER.len()];
+    decoder.read_exact(&mut buf)?;
+    if buf[..BCF_MAGIC_NUMBER.len()] == BCF_MAGIC_NUMBER {
+        return Ok(Format::Bcf);
+    }
+    if buf == VCF_HEADER {
+        return Ok(Format::Vcf);
+    }
+
+    Err(Error::UnknownFormat {
+        observed: buf.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn vcf_bytes() -> Vec<u8> {
+        b"##fileformat=VCFv4.2\n#CHROM\tPOS\n".to_vec()
+    }
+
+    fn bcf_bytes() -> Vec<u8> {
+        b"BCF\x02\x02rest-of-header".to_vec()
     }
 
-    // unknown format
-    Err(io::Error::new(io::ErrorKind::InvalidData, "unknown format"))
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn detects_no_compression() {
+        let mut reader = vcf_bytes().as_slice();
+        assert_eq!(detect_compression(&mut reader).unwrap(), Compression::None);
+    }
+
+    #[test]
+    fn detects_bgzf_compression() {
+        let gzipped = gzip(&vcf_bytes());
+        let mut reader = gzipped.as_slice();
+        assert_eq!(detect_compression(&mut reader).unwrap(), Compression::Bgzf);
+    }
+
+    #[test]
+    fn detects_zstd_compression() {
+        let zstd_bytes = zstd::encode_all(vcf_bytes().as_slice(), 0).unwrap();
+        let mut reader = zstd_bytes.as_slice();
+        assert_eq!(detect_compression(&mut reader).unwrap(), Compression::Zstd);
+    }
+
+    #[test]
+    fn detects_uncompressed_vcf_and_bcf() {
+        let mut reader = vcf_bytes().as_slice();
+        assert_eq!(
+            detect_format(&mut reader, Compression::None).unwrap(),
+            Format::Vcf
+        );
+
+        let mut reader = bcf_bytes().as_slice();
+        assert_eq!(
+            detect_format(&mut reader, Compression::None).unwrap(),
+            Format::Bcf
+        );
+    }
+
+    #[test]
+    fn detects_unknown_uncompressed_format() {
+        let mut reader = b"not a variant file at all".as_slice();
+        let err = detect_format(&mut reader, Compression::None).unwrap_err();
+        assert!(matches!(err, Error::UnknownFormat { .. }));
+    }
+
+    #[test]
+    fn detects_bgzf_compressed_vcf_and_bcf() {
+        let gzipped = gzip(&vcf_bytes());
+        let mut reader = gzipped.as_slice();
+        assert_eq!(
+            detect_format(&mut reader, Compression::Bgzf).unwrap(),
+            Format::Vcf
+        );
+
+        let gzipped = gzip(&bcf_bytes());
+        let mut reader = gzipped.as_slice();
+        assert_eq!(
+            detect_format(&mut reader, Compression::Bgzf).unwrap(),
+            Format::Bcf
+        );
+    }
+
+    #[test]
+    fn detects_zstd_compressed_vcf_and_bcf() {
+        let zstd_bytes = zstd::encode_all(vcf_bytes().as_slice(), 0).unwrap();
+        let mut reader = zstd_bytes.as_slice();
+        assert_eq!(
+            detect_format(&mut reader, Compression::Zstd).unwrap(),
+            Format::Vcf
+        );
+
+        let zstd_bytes = zstd::encode_all(bcf_bytes().as_slice(), 0).unwrap();
+        let mut reader = zstd_bytes.as_slice();
+        assert_eq!(
+            detect_format(&mut reader, Compression::Zstd).unwrap(),
+            Format::Bcf
+        );
+    }
+
+    #[test]
+    fn detection_never_consumes_the_input() {
+        // `Reader::from_reader` relies on `detect_compression`/`detect_format`
+        // only peeking: once both have run, the full original bytes must
+        // still be there for htslib to read, with nothing missing or
+        // duplicated.
+        let data = vcf_bytes();
+        let mut reader = data.as_slice();
+
+        let compression = detect_compression(&mut reader).unwrap();
+        detect_format(&mut reader, compression).unwrap();
+
+        assert_eq!(reader, data.as_slice());
+    }
 }
@@ -0,0 +1,39 @@
+use std::io;
+
+use crate::detect::Compression;
+
+/// The error type for this crate.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Neither the BCF magic number nor a VCF header line was found at the
+    /// start of the input. `observed` holds the leading bytes the detector
+    /// actually inspected, so callers can report what was really on the wire.
+    #[error("unknown format: observed {observed:02x?}")]
+    UnknownFormat { observed: Vec<u8> },
+
+    /// The input uses a compression scheme this crate cannot decode in the
+    /// current context.
+    #[error("unsupported compression: {0:?}")]
+    UnsupportedCompression(Compression),
+
+    /// A contig name isn't in the header it was looked up in: `skip_to` was
+    /// asked for an unknown contig, or a `MergeReader` input holds a contig
+    /// absent from the unified header.
+    #[error("contig not found: {0}")]
+    ContigNotFound(String),
+
+    /// `MergeReader::new` was given no inputs to merge.
+    #[error("MergeReader needs at least one input")]
+    EmptyMerge,
+
+    /// An error surfaced from the underlying htslib bindings.
+    #[error(transparent)]
+    Htslib(#[from] rust_htslib::errors::Error),
+
+    /// An I/O error.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A `Result` alias that defaults to this crate's [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -1,11 +1,28 @@
 pub use rust_htslib;
 use rust_htslib::bcf::{self, Read};
 //use rust_htslib::htslib as hts;
-use std::{io, path::Path};
+use std::io::{self, BufRead};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::thread;
+
+mod detect;
+mod error;
+mod merge;
+mod writer;
+
+pub use detect::{Compression, Format};
+pub use error::{Error, Result};
+pub use merge::MergeReader;
+pub use writer::Writer;
 
 pub enum ReaderInner {
     Indexed(bcf::IndexedReader),
     Plain(bcf::Reader),
+    /// A reader built from an arbitrary stream (e.g. a pipe or stdin) via
+    /// [`Reader::from_reader`]. Region access isn't possible without an
+    /// index, so it scans linearly just like `Plain`.
+    Stream(bcf::Reader),
 }
 
 pub struct Reader<'a> {
@@ -16,38 +33,81 @@ pub struct Reader<'a> {
     last_record: TinyRecord,
 }
 
-#[derive(Clone, Debug)]
-struct TinyRecord {
-    rid: i32,
-    pos: i64,
-    stop: i64,
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TinyRecord {
+    pub(crate) rid: i32,
+    pub(crate) pos: i64,
+    pub(crate) stop: i64,
 }
 
 impl Reader<'_> {
-    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Reader<'static>> {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Reader<'static>> {
         // check if .csi or .tbi index exists
         let has_index = Path::new(&format!("{}.csi", path.as_ref().display())).exists()
             || Path::new(&format!("{}.tbi", path.as_ref().display())).exists();
 
         if has_index {
             // Use indexed reader if index exists
-            return match bcf::IndexedReader::from_path(path) {
-                Ok(indexed_reader) => Ok(Reader::new(ReaderInner::Indexed(indexed_reader))),
-                Err(e) => {
-                    return Err(io::Error::new(io::ErrorKind::Other, e));
-                }
-            };
+            let indexed_reader = bcf::IndexedReader::from_path(path)?;
+            return Ok(Reader::new(ReaderInner::Indexed(indexed_reader)));
         }
+
+        // htslib has no zstd decoder, so peek for it up front and, if
+        // found, route through `from_reader`'s decode-on-the-fly pipe
+        // instead of handing htslib a file it can't open.
+        let mut file = io::BufReader::new(std::fs::File::open(path.as_ref())?);
+        if detect::detect_compression(&mut file)? == Compression::Zstd {
+            return Self::from_reader(file);
+        }
+
         // Use plain reader if no index exists
-        let reader =
-            bcf::Reader::from_path(path).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let reader = bcf::Reader::from_path(path)?;
         Ok(Reader::new(ReaderInner::Plain(reader)))
     }
 
+    /// Builds a `Reader` from any buffered stream, such as a pipe or stdin,
+    /// that cannot be opened by path.
+    ///
+    /// The stream is peeked with `detect_compression`/`detect_format` to
+    /// validate it up front with a proper [`Error`] instead of an opaque
+    /// htslib failure. Both only ever call `fill_buf`, never `consume`, so
+    /// the peeked bytes are still sitting at the front of `reader` afterward
+    /// -- `reader` itself, untouched, is everything downstream needs to see.
+    /// htslib only opens files by path, so the stream is pumped through a
+    /// pipe on a background thread and handed to htslib via `/dev/fd/<n>`,
+    /// which lets htslib do what it already does for BGZF/VCF/BCF without
+    /// this crate duplicating that decoding. htslib has no zstd decoder
+    /// though, so a zstd-compressed stream is decoded on that same
+    /// background thread before it reaches the pipe, mirroring how
+    /// [`crate::Writer::to_writer`] re-encodes zstd on the write side.
+    pub fn from_reader<R: BufRead + Send + 'static>(mut reader: R) -> Result<Reader<'static>> {
+        let compression = detect::detect_compression(&mut reader)?;
+        detect::detect_format(&mut reader, compression)?;
+
+        let (pipe_reader, mut pipe_writer) = io::pipe()?;
+        thread::spawn(move || -> io::Result<()> {
+            match compression {
+                Compression::Zstd => {
+                    let mut decoder = zstd::stream::read::Decoder::new(reader)?;
+                    io::copy(&mut decoder, &mut pipe_writer)?;
+                }
+                Compression::Bgzf | Compression::None => {
+                    io::copy(&mut reader, &mut pipe_writer)?;
+                }
+            }
+            Ok(())
+        });
+
+        let path = format!("/dev/fd/{}", pipe_reader.as_raw_fd());
+        let reader = bcf::Reader::from_path(&path)?;
+        Ok(Reader::new(ReaderInner::Stream(reader)))
+    }
+
     pub fn new(inner: ReaderInner) -> Self {
         let header = match &inner {
             ReaderInner::Indexed(r) => r.header().clone(),
             ReaderInner::Plain(r) => r.header().clone(),
+            ReaderInner::Stream(r) => r.header().clone(),
         };
         Self {
             inner,
@@ -75,7 +135,7 @@ impl Reader<'_> {
         }
     }
 
-    pub fn next_record(&mut self) -> io::Result<Option<bcf::Record>> {
+    pub fn next_record(&mut self) -> Result<Option<bcf::Record>> {
         if let Some(record) = self.take() {
             return Ok(Some(record));
         }
@@ -84,22 +144,21 @@ impl Reader<'_> {
             ReaderInner::Indexed(reader) => {
                 let mut record = reader.empty_record();
                 if let Some(records) = self.records.as_mut() {
-                    return records
-                        .next()
-                        .transpose()
-                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+                    return Ok(records.next().transpose()?);
                 }
                 match reader.read(&mut record) {
                     Some(Ok(())) => Ok(Some(record)),
-                    Some(Err(e)) => Err(io::Error::new(io::ErrorKind::Other, e)),
+                    Some(Err(e)) => Err(e.into()),
+                    None => Ok(None),
+                }
+            }
+            ReaderInner::Plain(reader) | ReaderInner::Stream(reader) => {
+                match reader.records().next() {
+                    Some(Ok(record)) => Ok(Some(record)),
+                    Some(Err(e)) => Err(e.into()),
                     None => Ok(None),
                 }
             }
-            ReaderInner::Plain(reader) => match reader.records().next() {
-                Some(Ok(record)) => Ok(Some(record)),
-                Some(Err(e)) => Err(io::Error::new(io::ErrorKind::Other, e)),
-                None => Ok(None),
-            },
         }
     }
 
@@ -127,21 +186,19 @@ fn is_record_after_last(last: &TinyRecord, record: &bcf::Record) -> bool {
 }
 
 pub trait Skip {
-    fn skip_to(&mut self, chrom: &str, pos0: u64) -> io::Result<()>;
+    fn skip_to(&mut self, chrom: &str, pos0: u64) -> Result<()>;
 }
 
 impl Skip for Reader<'_> {
-    fn skip_to(&mut self, chrom: &str, pos0: u64) -> io::Result<()> {
+    fn skip_to(&mut self, chrom: &str, pos0: u64) -> Result<()> {
         match &mut self.inner {
             ReaderInner::Indexed(reader) => {
                 let rid = reader
                     .header()
                     .name2rid(chrom.as_bytes())
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    .map_err(|_| Error::ContigNotFound(chrom.to_string()))?;
 
-                reader
-                    .fetch(rid, pos0, None)
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                reader.fetch(rid, pos0, None)?;
 
                 // Read until we find a record after the last_record
                 let mut record = reader.empty_record();
@@ -160,7 +217,7 @@ impl Skip for Reader<'_> {
                                 return Ok(());
                             }
                         }
-                        Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+                        Some(Err(e)) => return Err(e.into()),
                         None => {
                             break;
                         }
@@ -175,11 +232,13 @@ impl Skip for Reader<'_> {
                 let name = String::from_utf8(name.unwrap().to_vec()).unwrap();
                 self.skip_to(&name, 0)
             }
-            ReaderInner::Plain(reader) => {
+            // A stream has no index, so fall back to the same linear scan
+            // used for an unindexed file.
+            ReaderInner::Plain(reader) | ReaderInner::Stream(reader) => {
                 let target_rid = reader
                     .header()
                     .name2rid(chrom.as_bytes())
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    .map_err(|_| Error::ContigNotFound(chrom.to_string()))?;
 
                 // Scan through records until we find one that's >= our target position
                 // AND after our last_record
@@ -204,7 +263,7 @@ impl Skip for Reader<'_> {
                             }
                             // Otherwise continue scanning
                         }
-                        Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+                        Some(Err(e)) => return Err(e.into()),
                         None => return Ok(()),
                     }
                 }
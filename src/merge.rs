@@ -0,0 +1,244 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use rust_htslib::bcf;
+
+use crate::error::{Error, Result};
+use crate::{Reader, Skip, TinyRecord};
+
+/// One pending record pulled from a child reader, ordered by `key` so the
+/// `BinaryHeap` in [`MergeReader`] acts as a min-heap over it.
+struct Entry {
+    key: TinyRecord,
+    source: usize,
+    record: bcf::Record,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+impl Eq for Entry {}
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// A k-way merge over several coordinate-sorted VCF/BCF readers, yielding
+/// records in `(rid, pos, end)` order across all of them.
+///
+/// Each input keeps its own header and contig numbering, so ordering is
+/// resolved by contig *name*: a record's `rid` is mapped through its own
+/// reader's `rid2name`, then into the unified contig order taken from the
+/// first reader's header via `name2rid`. An input whose contig isn't present
+/// in that unified header is an error rather than a silent drop.
+pub struct MergeReader<'a> {
+    readers: Vec<Reader<'a>>,
+    unified_header: bcf::header::HeaderView,
+    heap: BinaryHeap<Reverse<Entry>>,
+}
+
+impl MergeReader<'static> {
+    /// Builds a merge over `readers`, priming the heap with one record from
+    /// each. The unified contig order is taken from `readers[0]`'s header.
+    pub fn new(readers: Vec<Reader<'static>>) -> Result<Self> {
+        let unified_header = readers
+            .first()
+            .ok_or(Error::EmptyMerge)?
+            .header()
+            .clone();
+
+        let mut merged = Self {
+            readers,
+            unified_header,
+            heap: BinaryHeap::new(),
+        };
+        for source in 0..merged.readers.len() {
+            merged.refill(source)?;
+        }
+        Ok(merged)
+    }
+
+    /// Pulls the next record from input `source` and, if there is one, maps
+    /// it into the unified contig order and pushes it onto the heap.
+    fn refill(&mut self, source: usize) -> Result<()> {
+        if let Some(record) = self.readers[source].next_record()? {
+            let rid = self.readers[source]
+                .header()
+                .rid2name(record.rid().unwrap())?;
+            let unified_rid = self
+                .unified_header
+                .name2rid(rid)
+                .map_err(|_| Error::ContigNotFound(String::from_utf8_lossy(rid).into_owned()))?
+                as i32;
+
+            self.heap.push(Reverse(Entry {
+                key: TinyRecord {
+                    rid: unified_rid,
+                    pos: record.pos(),
+                    stop: record.end(),
+                },
+                source,
+                record,
+            }));
+        }
+        Ok(())
+    }
+
+    /// Returns the next record in unified coordinate order, or `None` once
+    /// every input is exhausted.
+    pub fn next_record(&mut self) -> Result<Option<bcf::Record>> {
+        let Reverse(Entry { source, record, .. }) = match self.heap.pop() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.refill(source)?;
+        Ok(Some(record))
+    }
+
+    /// The unified header, i.e. the header of the first input.
+    pub fn header(&self) -> &bcf::header::HeaderView {
+        &self.unified_header
+    }
+}
+
+impl Skip for MergeReader<'static> {
+    fn skip_to(&mut self, chrom: &str, pos0: u64) -> Result<()> {
+        // Clear up front: if a later child's `skip_to` errors out, we'd
+        // otherwise be left with some children already skipped and others
+        // not, with stale pre-skip heap entries still mixed in.
+        self.heap.clear();
+        for reader in &mut self.readers {
+            reader.skip_to(chrom, pos0)?;
+        }
+        for source in 0..self.readers.len() {
+            self.refill(source)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reader;
+    use rust_htslib::bcf::Write as BcfWrite;
+
+    #[test]
+    fn rejects_empty_input() {
+        let err = MergeReader::new(Vec::new()).unwrap_err();
+        assert!(matches!(err, Error::EmptyMerge));
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("xvcf-rs-merge-test-{}-{name}", std::process::id()))
+    }
+
+    /// Writes a small plain-text VCF at `path` declaring `contigs` (in that
+    /// order) and containing one record per `(contig, pos0)` pair, via a
+    /// throwaway htslib writer -- there are no on-disk fixtures to read from.
+    fn write_vcf(path: &std::path::Path, contigs: &[&str], records: &[(&str, i64)]) {
+        let mut header = bcf::Header::new();
+        for contig in contigs {
+            let line = format!("##contig=<ID={contig},length=1000000>");
+            header.push_record(line.as_bytes());
+        }
+
+        let mut writer = bcf::Writer::from_path(path, &header, true, bcf::Format::Vcf).unwrap();
+        for (contig, pos0) in records {
+            let rid = writer.header().name2rid(contig.as_bytes()).unwrap();
+            let mut record = writer.empty_record();
+            record.set_rid(Some(rid));
+            record.set_pos(*pos0);
+            record.set_alleles(&[b"A", b"T"]).unwrap();
+            writer.write(&record).unwrap();
+        }
+    }
+
+    #[test]
+    fn merges_records_by_name_across_differently_ordered_headers() {
+        let path_a = temp_path("a.vcf");
+        let path_b = temp_path("b.vcf");
+        // `b`'s contigs are declared in the opposite order of `a`'s, so a
+        // correct merge has to resolve rid by *name*, not by raw index. Both
+        // inputs are still sorted in the unified (chr1, chr2) order.
+        write_vcf(&path_a, &["chr1", "chr2"], &[("chr1", 100), ("chr2", 50)]);
+        write_vcf(&path_b, &["chr2", "chr1"], &[("chr1", 200), ("chr2", 10)]);
+
+        let reader_a = Reader::from_path(&path_a).unwrap();
+        let reader_b = Reader::from_path(&path_b).unwrap();
+        let mut merged = MergeReader::new(vec![reader_a, reader_b]).unwrap();
+
+        let mut seen = Vec::new();
+        while let Some(record) = merged.next_record().unwrap() {
+            // Records keep their own input's header and rid numbering.
+            let name = record
+                .header()
+                .rid2name(record.rid().unwrap())
+                .unwrap()
+                .to_vec();
+            seen.push((String::from_utf8(name).unwrap(), record.pos()));
+        }
+
+        // Unified order is `a`'s (chr1, chr2), so every chr1 record sorts
+        // before every chr2 record, and within a contig they're by position.
+        assert_eq!(
+            seen,
+            vec![
+                ("chr1".to_string(), 100),
+                ("chr1".to_string(), 200),
+                ("chr2".to_string(), 10),
+                ("chr2".to_string(), 50),
+            ]
+        );
+
+        let _ = std::fs::remove_file(&path_a);
+        let _ = std::fs::remove_file(&path_b);
+    }
+
+    #[test]
+    fn errors_on_a_contig_absent_from_the_unified_header() {
+        let path_a = temp_path("unified.vcf");
+        let path_b = temp_path("foreign.vcf");
+        write_vcf(&path_a, &["chr1"], &[("chr1", 1)]);
+        write_vcf(&path_b, &["chr3"], &[("chr3", 1)]);
+
+        let reader_a = Reader::from_path(&path_a).unwrap();
+        let reader_b = Reader::from_path(&path_b).unwrap();
+        let err = MergeReader::new(vec![reader_a, reader_b]).unwrap_err();
+        assert!(matches!(err, Error::ContigNotFound(_)));
+
+        let _ = std::fs::remove_file(&path_a);
+        let _ = std::fs::remove_file(&path_b);
+    }
+
+    #[test]
+    fn skip_to_advances_every_child() {
+        let path_a = temp_path("skip-a.vcf");
+        let path_b = temp_path("skip-b.vcf");
+        write_vcf(&path_a, &["chr1", "chr2"], &[("chr1", 1), ("chr2", 50)]);
+        write_vcf(&path_b, &["chr1", "chr2"], &[("chr1", 2), ("chr2", 10)]);
+
+        let reader_a = Reader::from_path(&path_a).unwrap();
+        let reader_b = Reader::from_path(&path_b).unwrap();
+        let mut merged = MergeReader::new(vec![reader_a, reader_b]).unwrap();
+
+        merged.skip_to("chr2", 0).unwrap();
+
+        let mut seen = Vec::new();
+        while let Some(record) = merged.next_record().unwrap() {
+            seen.push(record.pos());
+        }
+        assert_eq!(seen, vec![10, 50]);
+
+        let _ = std::fs::remove_file(&path_a);
+        let _ = std::fs::remove_file(&path_b);
+    }
+}
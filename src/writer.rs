@@ -0,0 +1,240 @@
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::thread;
+
+use rust_htslib::bcf::{self, Write as BcfWrite};
+
+use crate::detect::{Compression, Format};
+use crate::error::Result;
+
+/// Writes VCF/BCF records, transcoding between format and compression the
+/// same way [`crate::Reader`] reads them.
+///
+/// A `Writer` always drives an htslib `bcf::Writer` internally; for
+/// [`Compression::Zstd`], which htslib cannot produce itself, htslib is
+/// handed an uncompressed stream and a background thread re-encodes it with
+/// zstd on the way to the destination.
+pub struct Writer {
+    // `Option` so `Drop` can close `inner` (and with it the pipe write end)
+    // before joining `copier` below -- otherwise the copier's read would
+    // block forever waiting for a writer that's still open.
+    inner: Option<bcf::Writer>,
+    copier: Option<thread::JoinHandle<io::Result<()>>>,
+}
+
+fn htslib_format(format: Format) -> bcf::Format {
+    match format {
+        Format::Vcf => bcf::Format::Vcf,
+        Format::Bcf => bcf::Format::Bcf,
+    }
+}
+
+impl Writer {
+    /// Opens a writer at `path`, transcoding to `format`/`compression`.
+    ///
+    /// `header` is typically cloned from a [`crate::Reader::header`].
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
+        header: &bcf::header::HeaderView,
+        format: Format,
+        compression: Compression,
+    ) -> Result<Self> {
+        match compression {
+            Compression::Zstd => {
+                let file = std::fs::File::create(path)?;
+                Self::to_writer(file, header, format, compression)
+            }
+            Compression::Bgzf | Compression::None => {
+                let htslib_header = bcf::Header::from_template(header);
+                let inner = bcf::Writer::from_path(
+                    path,
+                    &htslib_header,
+                    compression == Compression::None,
+                    htslib_format(format),
+                )?;
+                Ok(Self {
+                    inner: Some(inner),
+                    copier: None,
+                })
+            }
+        }
+    }
+
+    /// Builds a writer over any `Write` destination, such as a pipe or an
+    /// in-memory buffer, transcoding to `format`/`compression`.
+    ///
+    /// htslib only opens files by path, so `header`'s records are always
+    /// routed through an OS pipe; a background thread drains the read end
+    /// into `writer`, re-encoding with zstd first when that's the requested
+    /// compression.
+    pub fn to_writer<W: Write + Send + 'static>(
+        writer: W,
+        header: &bcf::header::HeaderView,
+        format: Format,
+        compression: Compression,
+    ) -> Result<Self> {
+        let (pipe_reader, pipe_writer) = io::pipe()?;
+
+        let copier = thread::spawn(move || -> io::Result<()> {
+            let mut pipe_reader = pipe_reader;
+            match compression {
+                Compression::Zstd => {
+                    let mut encoder = zstd::stream::write::Encoder::new(writer, 0)?;
+                    io::copy(&mut pipe_reader, &mut encoder)?;
+                    encoder.finish()?;
+                }
+                Compression::Bgzf | Compression::None => {
+                    let mut writer = writer;
+                    io::copy(&mut pipe_reader, &mut writer)?;
+                }
+            }
+            Ok(())
+        });
+
+        // htslib always writes its own format/compression choice here; zstd
+        // is layered on top by the copier thread above, so htslib itself
+        // only ever needs to produce Bgzf or uncompressed bytes.
+        let htslib_header = bcf::Header::from_template(header);
+        let path = format!("/dev/fd/{}", pipe_writer.as_raw_fd());
+        let inner = bcf::Writer::from_path(
+            path,
+            &htslib_header,
+            compression != Compression::Bgzf,
+            htslib_format(format),
+        )?;
+
+        Ok(Self {
+            inner: Some(inner),
+            copier: Some(copier),
+        })
+    }
+
+    /// Writes a single record, translating it into this writer's header.
+    pub fn write_record(&mut self, record: &bcf::Record) -> Result<()> {
+        let inner = self.inner.as_mut().expect("Writer used after drop");
+        let mut record = record.clone();
+        inner.translate(&mut record);
+        inner.write(&record)?;
+        Ok(())
+    }
+
+    /// Builds an empty record tied to this writer's header, ready to be
+    /// filled in and passed to [`Writer::write_record`].
+    pub fn empty_record(&self) -> bcf::Record {
+        self.inner
+            .as_ref()
+            .expect("Writer used after drop")
+            .empty_record()
+    }
+
+    pub fn header(&self) -> &bcf::header::HeaderView {
+        self.inner
+            .as_ref()
+            .expect("Writer used after drop")
+            .header()
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        // Drop htslib's writer first so its end of the pipe closes and the
+        // copier thread's `io::copy` sees EOF instead of blocking forever.
+        self.inner.take();
+        if let Some(copier) = self.copier.take() {
+            let _ = copier.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reader;
+    use std::io::Read as _;
+
+    #[test]
+    fn maps_our_format_onto_htslibs() {
+        assert!(matches!(htslib_format(Format::Vcf), bcf::Format::Vcf));
+        assert!(matches!(htslib_format(Format::Bcf), bcf::Format::Bcf));
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("xvcf-rs-writer-test-{}-{name}", std::process::id()))
+    }
+
+    /// A `HeaderView` with one contig, built without needing an on-disk
+    /// fixture: write it out with a throwaway htslib writer, then reuse the
+    /// `HeaderView` that writer hands back.
+    fn header_with_chr1() -> bcf::header::HeaderView {
+        let mut header = bcf::Header::new();
+        header.push_record(b"##contig=<ID=chr1,length=1000000>");
+
+        let scratch = temp_path("scratch.vcf");
+        let scratch_writer =
+            bcf::Writer::from_path(&scratch, &header, false, bcf::Format::Vcf).unwrap();
+        let view = scratch_writer.header().clone();
+        drop(scratch_writer);
+        let _ = std::fs::remove_file(&scratch);
+        view
+    }
+
+    #[test]
+    fn from_path_round_trips_a_record() {
+        let header = header_with_chr1();
+        let path = temp_path("round-trip.vcf");
+
+        {
+            let mut writer =
+                Writer::from_path(&path, &header, Format::Vcf, Compression::None).unwrap();
+            let mut record = writer.empty_record();
+            record.set_rid(Some(0));
+            record.set_pos(99);
+            writer.write_record(&record).unwrap();
+        }
+
+        let mut reader = Reader::from_path(&path).unwrap();
+        let record = reader
+            .next_record()
+            .unwrap()
+            .expect("expected the record we wrote");
+        assert_eq!(record.pos(), 99);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn dropping_a_to_writer_writer_joins_the_copier_thread() {
+        let header = header_with_chr1();
+        let path = temp_path("to-writer.vcf.gz");
+
+        {
+            let file = std::fs::File::create(&path).unwrap();
+            let mut writer =
+                Writer::to_writer(file, &header, Format::Vcf, Compression::Bgzf).unwrap();
+            let mut record = writer.empty_record();
+            record.set_rid(Some(0));
+            record.set_pos(5);
+            writer.write_record(&record).unwrap();
+            // `writer` drops here; if the copier thread weren't joined
+            // before `Drop` returns, the read below could race a still-
+            // draining pipe and see a truncated/empty file.
+        }
+
+        let mut magic = [0u8; 2];
+        std::fs::File::open(&path)
+            .unwrap()
+            .read_exact(&mut magic)
+            .unwrap();
+        assert_eq!(magic, [0x1f, 0x8b], "expected BGZF/gzip magic");
+
+        let mut reader = Reader::from_path(&path).unwrap();
+        let record = reader
+            .next_record()
+            .unwrap()
+            .expect("expected the record we wrote");
+        assert_eq!(record.pos(), 5);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}